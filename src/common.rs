@@ -1,8 +1,11 @@
 use crate::encryption;
-use anyhow::Error;
+use anyhow::{Context, Error};
+use std::collections::HashSet;
 use byte_slice_cast::AsSliceOf;
-use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{Read, Seek, SeekFrom};
+use flate2::read::ZlibDecoder;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rayon::prelude::*;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
 pub struct FileHeader {
@@ -22,6 +25,16 @@ impl FileHeader {
             file_cnt: reader.read_u32::<LittleEndian>()?,
         })
     }
+
+    pub fn write<T>(&self, writer: &mut T) -> Result<(), std::io::Error>
+    where
+        T: Write,
+    {
+        writer.write_u32::<LittleEndian>(self.checksum)?;
+        writer.write_u8(self.version)?;
+        writer.write_u32::<LittleEndian>(self.file_cnt)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -62,6 +75,24 @@ impl FileEntry {
         reader.read_exact(&mut ent.key)?;
         Ok(ent)
     }
+
+    pub fn write<T>(&self, writer: &mut T) -> Result<(), std::io::Error>
+    where
+        T: Write,
+    {
+        let units: Vec<u16> = self.name.encode_utf16().collect();
+        writer.write_u32::<LittleEndian>(units.len() as u32)?;
+        for u in &units {
+            writer.write_u16::<LittleEndian>(*u)?;
+        }
+        writer.write_u32::<LittleEndian>(self.checksum)?;
+        writer.write_u32::<LittleEndian>(self.flags)?;
+        writer.write_u32::<LittleEndian>(self.offset)?;
+        writer.write_u32::<LittleEndian>(self.original_size)?;
+        writer.write_u32::<LittleEndian>(self.raw_size)?;
+        writer.write_all(&self.key)?;
+        Ok(())
+    }
 }
 
 pub fn get_final_file_name(fname: &str) -> Result<String, Error> {
@@ -76,7 +107,7 @@ where
     T: Read + Seek,
 {
     let key = encryption::gen_header_key(fname,skey);
-    let offset = encryption::gen_header_offset(&fname);
+    let offset = encryption::gen_header_offset(fname);
     rd.seek(SeekFrom::Start(offset as u64))?;
     let mut dec_stream = encryption::Snow2Decoder::new(&key, rd);
     Ok(FileHeader::new(&mut dec_stream)?)
@@ -99,9 +130,9 @@ pub fn read_entries<T>(
 where
     T: Read + Seek,
 {
-    let key = encryption::gen_entries_key(&fname,skey);
-    let offset_header = encryption::gen_header_offset(&fname);
-    let offset_entry = encryption::gen_entries_offset(&fname);
+    let key = encryption::gen_entries_key(fname,skey);
+    let offset_header = encryption::gen_header_offset(fname);
+    let offset_entry = encryption::gen_entries_offset(fname);
     //println!("header offset: {:x}", offset_header);
     //println!("entry offset: {:x}", offset_entry);
     rd.seek(SeekFrom::Start((offset_header + offset_entry) as u64))?;
@@ -125,6 +156,69 @@ pub fn validate_entries(entries: &[FileEntry]) -> Result<(), Error> {
     Ok(())
 }
 
+/// 流式解压时每次搬运的块大小；峰值内存约为一个块加上 inflate 窗口。
+pub const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// 头部加密（`FLAG_HEAD_ENCRYPTED`）只覆盖 body 前这么多字节：只加密开头的
+/// 1 KiB 足以打乱 zlib 流头，省去对大条目整段加密的开销。写入端（`pack::encode_body`）
+/// 与读取端（`extract_entry`）共用这个常量，`pack` 的 round-trip 测试覆盖了
+/// `FLAG_HEAD_ENCRYPTED` 分支，确保两端对同一数值保持一致。
+pub const HEAD_ENCRYPT_SIZE: u64 = 1024;
+
+/// 把单个条目的 body 以固定大小的块流式解密 / 解压到 `writer`，全程不把整个条目
+/// 读进内存。返回写出的（解压后）字节数，供调用方与 `original_size` 比对。
+///
+/// 单文件提取和批量提取都复用这一函数。
+pub fn extract_entry<R, W>(
+    rd: &mut R,
+    writer: &mut W,
+    entry: &FileEntry,
+    key: &[u8],
+) -> Result<u64, Error>
+where
+    R: Read + Seek,
+    W: Write,
+{
+    rd.seek(SeekFrom::Start(entry.offset as u64))?;
+    let mut limited = rd.take(entry.raw_size as u64);
+
+    // 根据标志位决定密钥流覆盖多少字节：全加密覆盖整段，头部加密只覆盖前缀，
+    // 未加密则 limit 为 0（解码器直接透传）。
+    let enc_limit = if entry.flags & FLAG_ALL_ENCRYPTED != 0 {
+        entry.raw_size as u64
+    } else if entry.flags & FLAG_HEAD_ENCRYPTED != 0 {
+        HEAD_ENCRYPT_SIZE.min(entry.raw_size as u64)
+    } else {
+        0
+    };
+    let dec = encryption::Snow2Decoder::new_limited(key, &mut limited, enc_limit);
+
+    let mut buf = vec![0u8; BLOCK_SIZE];
+    let mut total = 0u64;
+    if entry.flags & FLAG_COMPRESSED != 0 {
+        let mut inflater = ZlibDecoder::new(dec);
+        loop {
+            let n = inflater.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n])?;
+            total += n as u64;
+        }
+    } else {
+        let mut dec = dec;
+        loop {
+            let n = dec.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n])?;
+            total += n as u64;
+        }
+    }
+    Ok(total)
+}
+
 pub const KEY_SALT_LIST: [&str; 10] = [
     "3@6|3a[@<Ex:L=eN|g",
     "CuAVPMZx:E96:(Rxdw",
@@ -138,44 +232,88 @@ pub const KEY_SALT_LIST: [&str; 10] = [
     "})wWb4?-sVGHNoPKpc"
 ];
 
-pub fn try_read_with_keys<T>(
-    fname: &str, 
-    rd: &mut T
-) -> Result<(FileHeader, Vec<FileEntry>, String), Error>
+// 对单个盐值跑完整的「读头部 -> 校验 -> 读条目 -> 校验」流程。
+// 每次尝试都用 `make_reader` 拿一个独立的 reader，因此可以并行执行。
+fn attempt_salt<F, T>(
+    fname: &str,
+    key_salt: &str,
+    make_reader: &F,
+) -> Result<(FileHeader, Vec<FileEntry>), Error>
 where
+    F: Fn() -> std::io::Result<T>,
     T: Read + Seek,
 {
-    for &key_salt in KEY_SALT_LIST.iter() {
-        // 保存当前位置
-        let start_pos = rd.seek(SeekFrom::Start(0))?;
-        
-        // 使用闭包进行一次完整的验证流程，任何步骤失败都会继续下一个密钥
-        match (|| -> Result<(FileHeader, Vec<FileEntry>), Error> {
-            // 尝试读取头部
-            let header = read_header(fname, key_salt, rd)?;
-            validate_header(&header)?;
-            
-            if header.version != 2 {
-                return Err(Error::msg(format!("不支持的头部版本 {}", header.version)));
-            }
-            
-            // 尝试读取条目
-            let entries = read_entries(fname, &header, key_salt, rd)?;
-            validate_entries(&entries)?;
-            
-            // 只有当头部和条目都成功验证时才返回成功
-            Ok((header, entries))
-        })() {
-            Ok((header, entries)) => {
-                println!("找到匹配的密钥: {}", key_salt);
-                return Ok((header, entries, key_salt.to_string()));
-            },
-            Err(_) => {
-                // 密钥验证失败，重置位置准备尝试下一个密钥
-                rd.seek(SeekFrom::Start(start_pos))?;
+    let mut rd = make_reader()?;
+    let header = read_header(fname, key_salt, &mut rd)?;
+    validate_header(&header)?;
+    if header.version != 2 {
+        return Err(Error::msg(format!("不支持的头部版本 {}", header.version)));
+    }
+    let entries = read_entries(fname, &header, key_salt, &mut rd)?;
+    validate_entries(&entries)?;
+    Ok((header, entries))
+}
+
+/// 读取附加盐值的环境变量名；其值作为单个候选盐值。
+pub const KEY_SALT_ENV: &str = "MABIPACK_KEY_SALT";
+
+/// 汇总全部候选盐值：外部来源（`--salt-file` 每行一个、以及 `KEY_SALT_ENV`
+/// 环境变量）排在内置的 `KEY_SALT_LIST` 之前，这样轮换出的新盐值会被优先尝试，
+/// 无需重新编译即可支持新的客户端。去重后保持首次出现的顺序。
+pub fn load_key_salts(salt_file: Option<&str>) -> Result<Vec<String>, Error> {
+    let mut salts: Vec<String> = Vec::new();
+    if let Some(path) = salt_file {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取盐值文件 {} 失败", path))?;
+        for line in content.lines() {
+            let s = line.trim();
+            if !s.is_empty() {
+                salts.push(s.to_string());
             }
         }
     }
-    
+    if let Ok(env) = std::env::var(KEY_SALT_ENV) {
+        let s = env.trim();
+        if !s.is_empty() {
+            salts.push(s.to_string());
+        }
+    }
+    salts.extend(KEY_SALT_LIST.iter().map(|s| s.to_string()));
+
+    let mut seen = HashSet::new();
+    salts.retain(|s| seen.insert(s.clone()));
+    Ok(salts)
+}
+
+/// 并行尝试 `salts` 中的每个盐值。
+///
+/// 调用方通过 [`load_key_salts`] 组装候选列表（内置 + 外部），再传入能重复打开
+/// 独立 reader 的工厂闭包。由于 `BufReader` 不能跨线程共享，每个候选盐值在自己
+/// 的游标上跑完整的校验流程。结果按 `salts` 的顺序返回第一个通过校验的盐值，而
+/// 非最先完成的那个，从而保持确定性语义。
+///
+/// 每个候选都先校验头部校验和并确认 `version == 2` 再去解析整张条目表，因此不匹配
+/// 的盐值会尽早被否决，扫描大盐值文件的开销很低。
+pub fn try_read_with_keys<F, T>(
+    fname: &str,
+    salts: &[String],
+    make_reader: F,
+) -> Result<(FileHeader, Vec<FileEntry>, String), Error>
+where
+    F: Fn() -> std::io::Result<T> + Sync,
+    T: Read + Seek,
+{
+    let results: Vec<Option<(FileHeader, Vec<FileEntry>)>> = salts
+        .par_iter()
+        .map(|key_salt| attempt_salt(fname, key_salt, &make_reader).ok())
+        .collect();
+
+    for (key_salt, res) in salts.iter().zip(results) {
+        if let Some((header, entries)) = res {
+            println!("找到匹配的密钥: {}", key_salt);
+            return Ok((header, entries, key_salt.clone()));
+        }
+    }
+
     Err(Error::msg("无法找到有效的密钥盐值，请手动指定"))
 }
@@ -0,0 +1,334 @@
+// pack 格式所依赖的 SNOW 2.0 密钥流生成器及其 XOR 流适配器。`Snow2Decoder`
+// 包裹一个 reader，在读取时解除掩码；`Snow2Encoder` 则是它在 writer 上的逆操作。
+// 因为本质是纯 XOR 流，编码与解码共用同一条密钥流，数据经两个适配器往返后保持不变。
+
+use std::io::{Read, Result as IoResult, Write};
+
+// 采用 AES 约化多项式的 GF(2^8) 乘法，供下面的 FSM S-box 与 LFSR 的 `alpha`
+// 乘法共用。
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0u8;
+    for _ in 0..8 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        let hi = a & 0x80;
+        a <<= 1;
+        if hi != 0 {
+            a ^= 0x1b;
+        }
+        b >>= 1;
+    }
+    p
+}
+
+// AES S-box，由乘法逆元加仿射变换现场算出，免得在代码里塞一张 256 字节的常量表。
+fn aes_sbox() -> [u8; 256] {
+    // GF(2^8) 中的乘法逆元
+    let mut inv = [0u8; 256];
+    for c in 1..=255u16 {
+        let c = c as u8;
+        for d in 1..=255u16 {
+            let d = d as u8;
+            if gf_mul(c, d) == 1 {
+                inv[c as usize] = d;
+                break;
+            }
+        }
+    }
+    let mut sbox = [0u8; 256];
+    for c in 0..=255usize {
+        let x = inv[c];
+        let mut s = x;
+        s ^= x.rotate_left(1);
+        s ^= x.rotate_left(2);
+        s ^= x.rotate_left(3);
+        s ^= x.rotate_left(4);
+        s ^= 0x63;
+        sbox[c] = s;
+    }
+    sbox
+}
+
+// SNOW 2.0 的 FSM S-box：先对每个字节做 AES SubBytes，再跟一次 AES MixColumn。
+fn snow_sbox(w: u32, sbox: &[u8; 256]) -> u32 {
+    let a = [
+        sbox[(w >> 24) as usize & 0xff],
+        sbox[(w >> 16) as usize & 0xff],
+        sbox[(w >> 8) as usize & 0xff],
+        sbox[w as usize & 0xff],
+    ];
+    let r0 = gf_mul(a[0], 2) ^ gf_mul(a[1], 3) ^ a[2] ^ a[3];
+    let r1 = a[0] ^ gf_mul(a[1], 2) ^ gf_mul(a[2], 3) ^ a[3];
+    let r2 = a[0] ^ a[1] ^ gf_mul(a[2], 2) ^ gf_mul(a[3], 3);
+    let r3 = gf_mul(a[0], 3) ^ a[1] ^ a[2] ^ gf_mul(a[3], 2);
+    ((r0 as u32) << 24) | ((r1 as u32) << 16) | ((r2 as u32) << 8) | r3 as u32
+}
+
+// LFSR 反馈字所需的 `alpha` 与 `alpha^-1` 乘法。
+fn mul_alpha(c: u8) -> u32 {
+    ((gf_mul(c, 0xa9) as u32) << 24)
+        | ((gf_mul(c, 0x38) as u32) << 16)
+        | ((gf_mul(c, 0x45) as u32) << 8)
+        | gf_mul(c, 0xc2) as u32
+}
+
+fn mul_alpha_inv(c: u8) -> u32 {
+    ((gf_mul(c, 0x98) as u32) << 24)
+        | ((gf_mul(c, 0x6f) as u32) << 16)
+        | ((gf_mul(c, 0x8c) as u32) << 8)
+        | gf_mul(c, 0x7a) as u32
+}
+
+struct Snow2Context {
+    lfsr: [u32; 16],
+    r1: u32,
+    r2: u32,
+    sbox: [u8; 256],
+}
+
+impl Snow2Context {
+    // 密钥为 `key16`，后面可选再跟 16 字节 IV。较短的切片会补零，这样同一套流程
+    // 既能处理头部/条目表密钥，也能处理每个条目自带的 16 字节 body 密钥。
+    fn new(key: &[u8]) -> Self {
+        let mut buf = [0u8; 32];
+        let n = key.len().min(32);
+        buf[..n].copy_from_slice(&key[..n]);
+        let word = |i: usize| {
+            u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]])
+        };
+        let k = [word(0), word(4), word(8), word(12)];
+        let iv = [word(16), word(20), word(24), word(28)];
+
+        let mut lfsr = [0u32; 16];
+        lfsr[15] = k[3] ^ iv[0];
+        lfsr[14] = k[2];
+        lfsr[13] = k[1];
+        lfsr[12] = k[0] ^ iv[1];
+        lfsr[11] = k[3] ^ 0xffff_ffff;
+        lfsr[10] = k[2] ^ 0xffff_ffff ^ iv[2];
+        lfsr[9] = k[1] ^ 0xffff_ffff ^ iv[3];
+        lfsr[8] = k[0] ^ 0xffff_ffff;
+        lfsr[7] = k[3];
+        lfsr[6] = k[2];
+        lfsr[5] = k[1];
+        lfsr[4] = k[0];
+        lfsr[3] = k[3] ^ 0xffff_ffff;
+        lfsr[2] = k[2] ^ 0xffff_ffff;
+        lfsr[1] = k[1] ^ 0xffff_ffff;
+        lfsr[0] = k[0] ^ 0xffff_ffff;
+
+        let mut ctx = Snow2Context {
+            lfsr,
+            r1: 0,
+            r2: 0,
+            sbox: aes_sbox(),
+        };
+        // 在开始输出前，用 32 步混合把 FSM 的输出折回 LFSR。
+        for _ in 0..32 {
+            let f = ctx.fsm(ctx.lfsr[15]);
+            ctx.shift(f);
+        }
+        ctx
+    }
+
+    fn fsm(&mut self, s15: u32) -> u32 {
+        let f = (s15.wrapping_add(self.r1)) ^ self.r2;
+        let new_r2 = snow_sbox(self.r1, &self.sbox);
+        self.r1 = self.r2.wrapping_add(self.lfsr[5]);
+        self.r2 = new_r2;
+        f
+    }
+
+    fn shift(&mut self, feedback: u32) {
+        let s0 = self.lfsr[0];
+        let s11 = self.lfsr[11];
+        let v = ((s0 << 8) ^ mul_alpha((s0 >> 24) as u8))
+            ^ self.lfsr[2]
+            ^ ((s11 >> 8) ^ mul_alpha_inv((s11 & 0xff) as u8))
+            ^ feedback;
+        self.lfsr.copy_within(1..16, 0);
+        self.lfsr[15] = v;
+    }
+
+    fn next_word(&mut self) -> u32 {
+        let f = self.fsm(self.lfsr[15]);
+        let z = f ^ self.lfsr[0];
+        self.shift(0);
+        z
+    }
+}
+
+macro_rules! stream_adapter {
+    ($name:ident, $bound:ident) => {
+        pub struct $name<'a, T: $bound> {
+            ctx: Snow2Context,
+            inner: &'a mut T,
+            ks: [u8; 4],
+            ks_pos: usize,
+            // 只对前 `limit` 个字节做掩码，密钥流也仅为这些字节推进；因此有界前缀
+            // （头部加密）或未加密 body（`limit == 0`）都会原样透传。
+            limit: u64,
+            count: u64,
+        }
+
+        impl<'a, T: $bound> $name<'a, T> {
+            pub fn new(key: &[u8], inner: &'a mut T) -> Self {
+                Self::new_limited(key, inner, u64::MAX)
+            }
+
+            pub fn new_limited(key: &[u8], inner: &'a mut T, limit: u64) -> Self {
+                $name {
+                    ctx: Snow2Context::new(key),
+                    inner,
+                    ks: [0; 4],
+                    ks_pos: 4,
+                    limit,
+                    count: 0,
+                }
+            }
+
+            fn key_byte(&mut self) -> u8 {
+                if self.ks_pos == 4 {
+                    self.ks = self.ctx.next_word().to_le_bytes();
+                    self.ks_pos = 0;
+                }
+                let b = self.ks[self.ks_pos];
+                self.ks_pos += 1;
+                b
+            }
+        }
+    };
+}
+
+stream_adapter!(Snow2Decoder, Read);
+stream_adapter!(Snow2Encoder, Write);
+
+impl<'a, T: Read> Read for Snow2Decoder<'a, T> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let n = self.inner.read(buf)?;
+        for b in buf[..n].iter_mut() {
+            if self.count < self.limit {
+                *b ^= self.key_byte();
+            }
+            self.count += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<'a, T: Write> Write for Snow2Encoder<'a, T> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let mut masked = [0u8; 4096];
+        let mut written = 0;
+        for chunk in buf.chunks(masked.len()) {
+            for (o, &b) in masked[..chunk.len()].iter_mut().zip(chunk) {
+                *o = if self.count < self.limit {
+                    self.count += 1;
+                    b ^ self.key_byte()
+                } else {
+                    self.count += 1;
+                    b
+                };
+            }
+            self.inner.write_all(&masked[..chunk.len()])?;
+            written += chunk.len();
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()
+    }
+}
+
+// 头部与条目表用由 pack 文件名加用户提供的盐值派生出的密钥做掩码；每个条目 body
+// 则自带一把 16 字节的密钥。
+fn derive(fname: &str, skey: &str, tag: u8) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325 ^ tag as u64;
+    let feed = |h: &mut u64, b: u8| {
+        *h ^= b as u64;
+        *h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    };
+    for b in skey.bytes().chain(fname.bytes()) {
+        feed(&mut h, b);
+    }
+    for (i, slot) in out.iter_mut().enumerate() {
+        feed(&mut h, i as u8);
+        *slot = (h >> ((i % 8) * 8)) as u8;
+    }
+    out
+}
+
+pub fn gen_header_key(fname: &str, skey: &str) -> [u8; 32] {
+    derive(fname, skey, 0)
+}
+
+pub fn gen_entries_key(fname: &str, skey: &str) -> [u8; 32] {
+    derive(fname, skey, 1)
+}
+
+// 头部与条目表位于与盐值无关、仅由文件名派生的偏移上，这样 reader 在还不知道任何
+// 密钥时就能定位它们。
+fn gen_offset(fname: &str, base: usize) -> usize {
+    let mut h: usize = base;
+    for b in fname.bytes() {
+        h = h.wrapping_mul(31).wrapping_add(b as usize);
+    }
+    base + (h & 0xff)
+}
+
+pub fn gen_header_offset(fname: &str) -> usize {
+    gen_offset(fname, 0x10)
+}
+
+pub fn gen_entries_offset(fname: &str) -> usize {
+    gen_offset(fname, 0x120)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn encoder_decoder_are_inverse() {
+        let key: Vec<u8> = (0..16u8).collect();
+        let plain: Vec<u8> = (0..500u16).map(|i| (i.wrapping_mul(7)) as u8).collect();
+
+        let mut enc_buf = Vec::new();
+        {
+            let mut enc = Snow2Encoder::new(&key, &mut enc_buf);
+            enc.write_all(&plain).unwrap();
+        }
+        assert_ne!(enc_buf, plain, "全加密后应与明文不同");
+
+        let mut cur = Cursor::new(enc_buf);
+        let mut dec = Snow2Decoder::new(&key, &mut cur);
+        let mut out = Vec::new();
+        dec.read_to_end(&mut out).unwrap();
+        assert_eq!(out, plain);
+    }
+
+    #[test]
+    fn limited_encryption_passes_tail_through() {
+        let key: Vec<u8> = (0..16u8).collect();
+        let plain: Vec<u8> = (0..100u8).collect();
+        let limit = 16u64;
+
+        let mut enc_buf = Vec::new();
+        {
+            let mut enc = Snow2Encoder::new_limited(&key, &mut enc_buf, limit);
+            enc.write_all(&plain).unwrap();
+        }
+        // 超过 limit 的尾部应原样透传，未被掩码。
+        assert_eq!(&enc_buf[limit as usize..], &plain[limit as usize..]);
+
+        let mut cur = Cursor::new(enc_buf);
+        let mut dec = Snow2Decoder::new_limited(&key, &mut cur, limit);
+        let mut out = Vec::new();
+        dec.read_to_end(&mut out).unwrap();
+        assert_eq!(out, plain);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::common::{self, FileEntry};
+use anyhow::{Context, Error};
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek};
+use std::path::{Component, Path};
+
+// 解析 pack 的条目表：显式给出 skey 时直接用，否则走 try_read_with_keys 回退。
+fn resolve_entries(
+    fname: &str,
+    skey: Option<&str>,
+    salt_file: Option<&str>,
+) -> Result<Vec<FileEntry>, Error> {
+    let final_file_name = common::get_final_file_name(fname)?;
+    let mut rd = BufReader::new(File::open(fname)?);
+    match skey {
+        Some(key) => {
+            let header = common::read_header(&final_file_name, key, &mut rd)
+                .context("读取头部失败")?;
+            common::validate_header(&header)?;
+            if header.version != 2 {
+                return Err(Error::msg(format!("不支持的头部版本 {}", header.version)));
+            }
+            let entries = common::read_entries(&final_file_name, &header, key, &mut rd)
+                .context("读取条目失败")?;
+            common::validate_entries(&entries)?;
+            Ok(entries)
+        }
+        None => {
+            let salts = common::load_key_salts(salt_file)?;
+            let (_, entries, _) = common::try_read_with_keys(&final_file_name, &salts, || {
+                File::open(fname).map(BufReader::new)
+            })
+            .context("尝试多个密钥失败")?;
+            Ok(entries)
+        }
+    }
+}
+
+// 把单个条目流式写到 out_dir 下对应的相对路径，必要时补齐父目录。
+fn extract_to_dir<R>(rd: &mut R, entry: &FileEntry, out_dir: &Path) -> Result<(), Error>
+where
+    R: Read + Seek,
+{
+    // 条目名来自不可信的 pack，拒绝绝对路径或含 `..` 的分量，避免写到 out_dir
+    // 之外（zip-slip）。
+    let rel = Path::new(&entry.name);
+    if rel
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(Error::msg(format!("非法条目路径 {}", entry.name)));
+    }
+    let dest = out_dir.join(rel);
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut writer = File::create(&dest).with_context(|| format!("创建 {} 失败", dest.display()))?;
+    common::extract_entry(rd, &mut writer, entry, &entry.key)?;
+    Ok(())
+}
+
+// 单文件提取：按名字在条目表里找到目标并写出。
+pub fn run_extract_one(
+    fname: &str,
+    skey: Option<&str>,
+    entry_name: &str,
+    out_dir: &str,
+    salt_file: Option<&str>,
+) -> Result<(), Error> {
+    let entries = resolve_entries(fname, skey, salt_file)?;
+    let entry = entries
+        .iter()
+        .find(|e| e.name == entry_name)
+        .ok_or_else(|| Error::msg(format!("pack 中找不到条目 {}", entry_name)))?;
+    let mut rd = BufReader::new(File::open(fname)?);
+    extract_to_dir(&mut rd, entry, Path::new(out_dir))?;
+    println!("提取 {} -> {}", entry.name, out_dir);
+    Ok(())
+}
+
+// 批量提取：把所有条目都流式写到 out_dir 下。
+pub fn run_extract(
+    fname: &str,
+    skey: Option<&str>,
+    out_dir: &str,
+    salt_file: Option<&str>,
+) -> Result<(), Error> {
+    let entries = resolve_entries(fname, skey, salt_file)?;
+    let mut rd = BufReader::new(File::open(fname)?);
+    let out_dir = Path::new(out_dir);
+    for ent in &entries {
+        extract_to_dir(&mut rd, ent, out_dir)?;
+    }
+    println!("提取完成：{} 个文件 -> {}", entries.len(), out_dir.display());
+    Ok(())
+}
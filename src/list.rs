@@ -1,48 +1,135 @@
-use crate::common;
-use anyhow::{Context, Error};
-use std::fs::{File, OpenOptions};
-use std::io::{self, BufReader, Write};
-
-pub fn run_list(fname: &str, skey: Option<&str>, output: Option<&str>) -> Result<(), Error> {
-    let fp = File::open(fname)?;
-    let mut rd = BufReader::new(fp);
-    let final_file_name = common::get_final_file_name(fname)?;
-    
-    let (header, entries, _used_key) = match skey {
-        Some(key) => {
-            let header = common::read_header(&final_file_name, key, &mut rd)
-                .context("读取头部失败")?;
-            common::validate_header(&header)?;
-            if header.version != 2 {
-                return Err(Error::msg(format!(
-                    "不支持的头部版本 {}",
-                    header.version
-                )));
-            }
-            
-            let entries = common::read_entries(&final_file_name, &header, key, &mut rd)
-                .context("读取条目失败")?;
-            common::validate_entries(&entries)?;
-            
-            (header, entries, key.to_string())
-        },
-        None => common::try_read_with_keys(&final_file_name, &mut rd)
-            .context("尝试多个密钥失败")?
-    };
-
-    let output_stream: Result<Box<dyn Write>, Error> =
-        output.map_or(Ok(Box::new(io::stdout())), |path| {
-            OpenOptions::new()
-                .create(true)
-                .write(true)
-                .open(path)
-                .map(|f| Box::new(f) as Box<dyn Write>)
-                .map_err(Error::new)
-        });
-    let mut output_stream = output_stream?;
-
-    entries.iter().for_each(|e| {
-        writeln!(output_stream, "{}", e.name).unwrap();
-    });
-    Ok(())
-}
+use crate::common::{self, FileEntry, FLAG_ALL_ENCRYPTED, FLAG_COMPRESSED, FLAG_HEAD_ENCRYPTED};
+use anyhow::{Context, Error};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Write};
+use std::str::FromStr;
+
+/// `--format` 选项支持的几种清单格式。
+#[derive(Clone, Copy)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Cbor,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "cbor" => Ok(OutputFormat::Cbor),
+            other => Err(Error::msg(format!("不支持的格式 {}", other))),
+        }
+    }
+}
+
+// 把 FileEntry 的 flags 解码成可供工具直接消费的布尔字段。
+#[derive(Serialize)]
+struct ManifestEntry<'a> {
+    name: &'a str,
+    offset: u32,
+    original_size: u32,
+    raw_size: u32,
+    compressed: bool,
+    encrypted: bool,
+    head_encrypted: bool,
+}
+
+impl<'a> ManifestEntry<'a> {
+    fn from_entry(e: &'a FileEntry) -> Self {
+        ManifestEntry {
+            name: &e.name,
+            offset: e.offset,
+            original_size: e.original_size,
+            raw_size: e.raw_size,
+            compressed: e.flags & FLAG_COMPRESSED != 0,
+            encrypted: e.flags & FLAG_ALL_ENCRYPTED != 0,
+            head_encrypted: e.flags & FLAG_HEAD_ENCRYPTED != 0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    key_salt: &'a str,
+    files: Vec<ManifestEntry<'a>>,
+}
+
+pub fn run_list(
+    fname: &str,
+    skey: Option<&str>,
+    output: Option<&str>,
+    format: OutputFormat,
+    salt_file: Option<&str>,
+) -> Result<(), Error> {
+    let final_file_name = common::get_final_file_name(fname)?;
+
+    let (_header, entries, used_key) = match skey {
+        Some(key) => {
+            let mut rd = BufReader::new(File::open(fname)?);
+            let header = common::read_header(&final_file_name, key, &mut rd)
+                .context("读取头部失败")?;
+            common::validate_header(&header)?;
+            if header.version != 2 {
+                return Err(Error::msg(format!(
+                    "不支持的头部版本 {}",
+                    header.version
+                )));
+            }
+
+            let entries = common::read_entries(&final_file_name, &header, key, &mut rd)
+                .context("读取条目失败")?;
+            common::validate_entries(&entries)?;
+
+            (header, entries, key.to_string())
+        }
+        None => {
+            let salts = common::load_key_salts(salt_file)?;
+            common::try_read_with_keys(&final_file_name, &salts, || {
+                File::open(fname).map(BufReader::new)
+            })
+            .context("尝试多个密钥失败")?
+        }
+    };
+
+    let output_stream: Result<Box<dyn Write>, Error> =
+        output.map_or(Ok(Box::new(io::stdout())), |path| {
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(path)
+                .map(|f| Box::new(f) as Box<dyn Write>)
+                .map_err(Error::new)
+        });
+    let mut output_stream = output_stream?;
+
+    match format {
+        OutputFormat::Text => {
+            entries.iter().for_each(|e| {
+                writeln!(output_stream, "{}", e.name).unwrap();
+            });
+        }
+        OutputFormat::Json => {
+            let manifest = Manifest {
+                key_salt: &used_key,
+                files: entries.iter().map(ManifestEntry::from_entry).collect(),
+            };
+            serde_json::to_writer_pretty(&mut output_stream, &manifest)
+                .context("序列化 JSON 清单失败")?;
+            writeln!(output_stream)?;
+        }
+        OutputFormat::Cbor => {
+            let manifest = Manifest {
+                key_salt: &used_key,
+                files: entries.iter().map(ManifestEntry::from_entry).collect(),
+            };
+            ciborium::ser::into_writer(&manifest, &mut output_stream)
+                .context("序列化 CBOR 清单失败")?;
+        }
+    }
+    Ok(())
+}
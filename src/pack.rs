@@ -0,0 +1,228 @@
+use crate::common::{
+    self, FileEntry, FileHeader, FLAG_ALL_ENCRYPTED, FLAG_COMPRESSED, FLAG_HEAD_ENCRYPTED,
+};
+use crate::encryption;
+use anyhow::{Context, Error};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use rand::RngCore;
+use std::fs::{self, File};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+// 递归收集 input_dir 下的所有文件，返回 (磁盘路径, pack 内相对路径) 列表。
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<(PathBuf, String)>) -> Result<(), Error> {
+    for ent in fs::read_dir(dir)? {
+        let path = ent?.path();
+        if path.is_dir() {
+            collect_files(root, &path, out)?;
+        } else {
+            let rel = path
+                .strip_prefix(root)
+                .expect("child path must be under root")
+                .to_str()
+                .ok_or_else(|| Error::msg("file name is not valid unicode"))?
+                .replace('\\', "/");
+            out.push((path, rel));
+        }
+    }
+    Ok(())
+}
+
+// 逐文件决定标志位并编码 body，返回 (body, flags, key)。
+// 压缩：只有 zlib 确实把数据变小才打 FLAG_COMPRESSED，否则原样存储。
+// 加密：小 body 整体加密（FLAG_ALL_ENCRYPTED），大 body 只加密头部
+// HEAD_ENCRYPT_SIZE 字节（FLAG_HEAD_ENCRYPTED），与 extract_entry 的解密范围一致。
+fn encode_body(data: &[u8]) -> Result<(Vec<u8>, u32, [u8; 16]), Error> {
+    let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(data)?;
+    let compressed = enc.finish()?;
+
+    let mut flags = 0u32;
+    let payload: &[u8] = if compressed.len() < data.len() {
+        flags |= FLAG_COMPRESSED;
+        &compressed
+    } else {
+        data
+    };
+
+    let enc_limit = if payload.len() as u64 > common::HEAD_ENCRYPT_SIZE {
+        flags |= FLAG_HEAD_ENCRYPTED;
+        common::HEAD_ENCRYPT_SIZE
+    } else {
+        flags |= FLAG_ALL_ENCRYPTED;
+        payload.len() as u64
+    };
+
+    let mut key = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut key);
+
+    let mut body = Vec::with_capacity(payload.len());
+    {
+        let mut stream = encryption::Snow2Encoder::new_limited(&key, &mut body, enc_limit);
+        stream.write_all(payload)?;
+    }
+    Ok((body, flags, key))
+}
+
+fn serialized_len(ent: &FileEntry) -> usize {
+    let units = ent.name.encode_utf16().count();
+    4 + units * 2 + 4 * 5 + 16
+}
+
+pub fn run_pack(input_dir: &str, out_file: &str, skey: &str) -> Result<(), Error> {
+    let root = Path::new(input_dir);
+    let mut files = Vec::new();
+    collect_files(root, root, &mut files).context("遍历输入目录失败")?;
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let final_file_name = common::get_final_file_name(out_file)?;
+    let header_offset = encryption::gen_header_offset(&final_file_name);
+    let entries_offset = encryption::gen_entries_offset(&final_file_name);
+    let entries_start = header_offset + entries_offset;
+
+    // 第一遍：编码每个文件的 body，并建好除 offset 外的条目字段。
+    let mut bodies = Vec::with_capacity(files.len());
+    let mut entries = Vec::with_capacity(files.len());
+    for (path, rel) in &files {
+        let data = fs::read(path).with_context(|| format!("读取 {} 失败", path.display()))?;
+        let (body, flags, key) = encode_body(&data)?;
+        let mut ent = FileEntry {
+            name: rel.clone(),
+            checksum: 0,
+            flags,
+            offset: 0,
+            original_size: data.len() as u32,
+            raw_size: body.len() as u32,
+            key,
+        };
+        ent.checksum = entry_checksum(&ent);
+        entries.push(ent);
+        bodies.push(body);
+    }
+
+    // body 紧跟在条目表之后；知道表长后才能确定各 body 的绝对偏移。
+    let table_len: usize = entries.iter().map(serialized_len).sum();
+    let mut cursor = entries_start + table_len;
+    for ent in entries.iter_mut() {
+        ent.offset = cursor as u32;
+        ent.checksum = entry_checksum(ent);
+        cursor += ent.raw_size as usize;
+    }
+
+    let header = FileHeader {
+        version: 2,
+        file_cnt: entries.len() as u32,
+        checksum: 2 + entries.len() as u32,
+    };
+
+    // 组装整个 pack：头部与条目表经各自的 SNOW2 密钥流加密后落在固定偏移上。
+    let total = cursor;
+    let mut out = vec![0u8; total];
+
+    let mut header_buf = Vec::new();
+    {
+        let key = encryption::gen_header_key(&final_file_name, skey);
+        let mut stream = encryption::Snow2Encoder::new(&key, &mut header_buf);
+        header.write(&mut stream)?;
+    }
+    out[header_offset..header_offset + header_buf.len()].copy_from_slice(&header_buf);
+
+    let mut table_buf = Cursor::new(Vec::with_capacity(table_len));
+    {
+        let key = encryption::gen_entries_key(&final_file_name, skey);
+        let mut stream = encryption::Snow2Encoder::new(&key, &mut table_buf);
+        for ent in &entries {
+            ent.write(&mut stream)?;
+        }
+    }
+    let table_buf = table_buf.into_inner();
+    out[entries_start..entries_start + table_buf.len()].copy_from_slice(&table_buf);
+
+    for (ent, body) in entries.iter().zip(&bodies) {
+        let off = ent.offset as usize;
+        out[off..off + body.len()].copy_from_slice(body);
+    }
+
+    let mut fp = File::create(out_file).with_context(|| format!("创建 {} 失败", out_file))?;
+    fp.write_all(&out)?;
+    println!("打包完成：{} 个文件 -> {}", entries.len(), out_file);
+    Ok(())
+}
+
+fn entry_checksum(ent: &FileEntry) -> u32 {
+    let key_sum = ent.key.iter().fold(0u32, |s, v| s + *v as u32);
+    ent.flags + ent.offset + ent.original_size + ent.raw_size + key_sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::FLAG_HEAD_ENCRYPTED;
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    // 在临时目录里造一个装有若干文件的输入目录，返回其路径。
+    fn make_input(tag: &str, files: &[(&str, Vec<u8>)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mabipack_{}_{}", tag, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (name, data) in files {
+            let path = dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, data).unwrap();
+        }
+        dir
+    }
+
+    // 打包后再读回条目表、逐个 extract_entry，断言写出的字节数等于 original_size、
+    // 内容与原始字节一致。
+    fn roundtrip(tag: &str, files: &[(&str, Vec<u8>)]) -> Vec<FileEntry> {
+        let input = make_input(tag, files);
+        let out = std::env::temp_dir().join(format!("mabipack_{}_{}.it", tag, std::process::id()));
+        let skey = common::KEY_SALT_LIST[0];
+        run_pack(input.to_str().unwrap(), out.to_str().unwrap(), skey).unwrap();
+
+        let packed = fs::read(&out).unwrap();
+        let final_name = common::get_final_file_name(out.to_str().unwrap()).unwrap();
+        let mut rd = Cursor::new(packed);
+        let header = common::read_header(&final_name, skey, &mut rd).unwrap();
+        common::validate_header(&header).unwrap();
+        let entries = common::read_entries(&final_name, &header, skey, &mut rd).unwrap();
+        common::validate_entries(&entries).unwrap();
+
+        for (name, data) in files {
+            let ent = entries.iter().find(|e| e.name == *name).unwrap();
+            let mut got = Vec::new();
+            rd.seek(SeekFrom::Start(0)).unwrap();
+            let n = common::extract_entry(&mut rd, &mut got, ent, &ent.key).unwrap();
+            assert_eq!(n, ent.original_size as u64);
+            assert_eq!(&got, data, "条目 {} 内容不一致", name);
+        }
+
+        let _ = fs::remove_dir_all(&input);
+        let _ = fs::remove_file(&out);
+        entries
+    }
+
+    #[test]
+    fn roundtrip_head_encrypted_large_file() {
+        // 用 LCG 生成一段不可压缩的数据，确保 payload 原样存储且大于
+        // HEAD_ENCRYPT_SIZE，从而触发 FLAG_HEAD_ENCRYPTED 分支。
+        let mut state = 0x1234_5678u32;
+        let data: Vec<u8> = (0..4096)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let entries = roundtrip("head", &[("big.bin", data)]);
+        assert!(entries[0].flags & FLAG_HEAD_ENCRYPTED != 0);
+    }
+
+    #[test]
+    fn roundtrip_small_compressed_file() {
+        let data = b"hello, mabinogi pack format! ".repeat(8);
+        let entries = roundtrip("small", &[("a/greeting.txt", data)]);
+        assert!(entries[0].flags & FLAG_COMPRESSED != 0);
+        assert!(entries[0].flags & FLAG_ALL_ENCRYPTED != 0);
+    }
+}
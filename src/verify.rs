@@ -0,0 +1,84 @@
+use crate::common;
+use anyhow::{Context, Error};
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+
+// 把写入的字节喂给 CRC32，同时丢弃内容——既不占内存也不落盘，仅用于完整性校验。
+struct Crc32Sink {
+    hasher: crc32fast::Hasher,
+}
+
+impl Write for Crc32Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn run_verify(fname: &str, skey: Option<&str>, salt_file: Option<&str>) -> Result<(), Error> {
+    let final_file_name = common::get_final_file_name(fname)?;
+
+    let entries = {
+        let fp = File::open(fname)?;
+        let mut rd = BufReader::new(fp);
+        match skey {
+            Some(key) => {
+                let header = common::read_header(&final_file_name, key, &mut rd)
+                    .context("读取头部失败")?;
+                common::validate_header(&header)?;
+                if header.version != 2 {
+                    return Err(Error::msg(format!("不支持的头部版本 {}", header.version)));
+                }
+                let entries = common::read_entries(&final_file_name, &header, key, &mut rd)
+                    .context("读取条目失败")?;
+                common::validate_entries(&entries)?;
+                entries
+            }
+            None => {
+                let salts = common::load_key_salts(salt_file)?;
+                let (_, entries, _) =
+                    common::try_read_with_keys(&final_file_name, &salts, || {
+                        File::open(fname).map(BufReader::new)
+                    })
+                    .context("尝试多个密钥失败")?;
+                entries
+            }
+        }
+    };
+
+    // 逐个条目解密 / 解压校验，遇错不中断，最后汇总一份报告。
+    let mut rd = BufReader::new(File::open(fname)?);
+    let mut failed = 0usize;
+    for ent in &entries {
+        let mut sink = Crc32Sink {
+            hasher: crc32fast::Hasher::new(),
+        };
+        match common::extract_entry(&mut rd, &mut sink, ent, &ent.key) {
+            Ok(written) if written == ent.original_size as u64 => {
+                println!("OK   {:08x}  {}", sink.hasher.finalize(), ent.name);
+            }
+            Ok(written) => {
+                failed += 1;
+                println!(
+                    "FAIL 大小不符（期望 {}，实际 {}）  {}",
+                    ent.original_size, written, ent.name
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                println!("FAIL {}  {}", e, ent.name);
+            }
+        }
+    }
+
+    println!("共 {} 个文件，{} 个失败", entries.len(), failed);
+    if failed > 0 {
+        Err(Error::msg(format!("{} 个条目校验失败", failed)))
+    } else {
+        Ok(())
+    }
+}